@@ -0,0 +1,8 @@
+//! Constants used throughout the kernel
+
+/// Length of a task's time slice, counted in timer interrupts (ticks), before
+/// the timer interrupt preempts it
+pub const TIME_SLICE: usize = 10;
+
+/// 步进(stride)调度器中每步的基准值，每次被调度的任务步进 BIG_STRIDE / priority
+pub const BIG_STRIDE: u64 = 65536;
@@ -1,7 +1,7 @@
 //! Process management syscalls
 use crate::{
     config::MAX_SYSCALL_NUM,
-    task::{exit_current_and_run_next, suspend_current_and_run_next, get_syscall_times, get_status, get_current_running_time, TaskStatus},
+    task::{exit_current_and_run_next, suspend_current_and_run_next, get_syscall_times, get_status, get_current_running_time, set_priority, TaskStatus},
     timer::get_time_us,
 };
 
@@ -21,7 +21,7 @@ pub struct TaskInfo {
     /// The numbers of syscall called by task
     syscall_times: [u32; MAX_SYSCALL_NUM],   // 任务使用的系统调用及调用次数, 在实验中系统调用号一定小于500， 所以使用一个长为MAX_SYSCALL_NUM=500的数组做桶计数
     /// Total running time of task
-    time: usize,          // 系统调用时刻距离任务第一次被调度时刻的时长(单位: ms), 这个时长可能包含该任务被其他任务抢占后的等待重新调度的时间
+    time: usize,          // 任务实际占用CPU的总时长(单位: ms), 不包含被抢占后等待重新调度的时间
 }
 
 /// task exits and submit an exit code
@@ -56,9 +56,22 @@ pub fn sys_get_time(ts: *mut TimeVal, _tz: usize) -> isize {
     0
 }
 
+/// set the priority of the current task for the stride scheduler
+/// 功能: 设置当前任务的调度优先级，优先级越高分到的CPU时间越多
+/// 参数prio: 新的优先级，必须 >= 2
+/// 返回值: 设置成功返回新的优先级，参数不合法返回-1
+pub fn sys_set_priority(prio: isize) -> isize {
+    trace!("kernel: sys_set_priority");
+    if prio < 2 {
+        return -1;
+    }
+    set_priority(prio as usize);
+    prio
+}
+
 /// YOUR JOB: Finish sys_task_info to pass testcases
 /// 查询正在执行的任务信息，任务信息包括任务控制块的相关信息(任务状态)，任务使用的系统调用和系统调用次数
-/// 系统调用时刻距离任务第一次被调度时刻的时长(单位ms)
+/// 任务实际占用CPU的总时长(单位ms)
 /// 参数ti: 待查询的任务信息
 /// 返回值: 执行成功返回0, 错误返回-1
 pub fn sys_task_info(_ti: *mut TaskInfo) -> isize {
@@ -14,13 +14,14 @@ mod switch;
 #[allow(clippy::module_inception)]
 mod task;
 
-use crate::config::{MAX_APP_NUM, MAX_SYSCALL_NUM};
+use crate::config::{BIG_STRIDE, MAX_APP_NUM, MAX_SYSCALL_NUM, TIME_SLICE};
 use crate::loader::{get_num_app, init_app_cx};
 use crate::sync::UPSafeCell;
-use crate::timer::get_time_ms;
+use crate::timer::{get_time_ms, set_next_trigger};
 use lazy_static::*;
+use riscv::register::sstatus;
 use switch::__switch;
-pub use task::{TaskControlBlock, TaskStatus};
+pub use task::{stride_less, TaskControlBlock, TaskStatus, DEFAULT_PRIORITY};
 
 pub use context::TaskContext;
 
@@ -59,7 +60,12 @@ lazy_static! {
             task_cx: TaskContext::zero_init(),
             task_status: TaskStatus::UnInit,
             syscall_times: [0; MAX_SYSCALL_NUM],   // 将当前任务的系统调用的次数都初始化为0
-            first_time: None,                      // None表示当前任务还没有被调度过
+            scheduled_in: None,                    // None表示当前任务还未处于Running状态
+            cpu_time: 0,
+            time_slice: TIME_SLICE,
+            priority: DEFAULT_PRIORITY,
+            stride: 0,
+            pass: BIG_STRIDE / DEFAULT_PRIORITY as u64,
         }; MAX_APP_NUM];
         // 依次对每个任务控制块进行初始化，将运行状态设置为Ready, 并且在其内核栈栈顶压入一些初始化上下文
         // 然后更新它的task_cx
@@ -99,8 +105,12 @@ impl TaskManager {
         task0.task_status = TaskStatus::Running;
         let next_task_cx_ptr = &task0.task_cx as *const TaskContext;
 
-        task0.first_time = Some(get_time_ms());       // 记录首次被调度的时间
+        task0.scheduled_in = Some(get_time_ms());     // 记录本次换入的时间
+        task0.time_slice = TIME_SLICE;                // 为第一个任务分配完整的时间片
         drop(inner);
+        crate::trap::init();
+        crate::trap::enable_timer_interrupt(); // 开启时钟中断，为抢占式调度做准备
+        set_next_trigger(); // 打开时钟中断，开启抢占式调度
         let mut _unused = TaskContext::zero_init();
         // before this, we should drop local variables that must be dropped manually
         unsafe {
@@ -109,12 +119,20 @@ impl TaskManager {
         panic!("unreachable in run_first_task!");
     }
 
+    /// 将任务自上次换入以来经过的时间计入cpu_time，并清空scheduled_in
+    fn account_cpu_time(task: &mut TaskControlBlock) {
+        if let Some(scheduled_in) = task.scheduled_in.take() { // 取出并清空上次换入的时间
+            task.cpu_time += get_time_ms() - scheduled_in;     // 累加这段Running区间的时长
+        }
+    }
+
     /// Change the status of current `Running` task into `Ready`.
     /// 先获得里层TaskManagerInner的可变引用，然后修改任务控制块数组tasks中当前任务的状态
     fn mark_current_suspended(&self) {
         let mut inner = self.inner.exclusive_access();
         let current = inner.current_task;  // current是当前运行的任务的编号
         inner.tasks[current].task_status = TaskStatus::Ready; // 修改当前运行的任务的状态
+        Self::account_cpu_time(&mut inner.tasks[current]);
     }
 
     /// Change the status of current `Running` task into `Exited`.
@@ -122,45 +140,96 @@ impl TaskManager {
         let mut inner = self.inner.exclusive_access();
         let current = inner.current_task;  // current是当前运行的任务的编号
         inner.tasks[current].task_status = TaskStatus::Exited; // 修改当前运行的任务的状态
+        Self::account_cpu_time(&mut inner.tasks[current]);
+    }
+
+    /// Change the status of current `Running` task into `Blocked`.
+    fn mark_current_blocked(&self) {
+        let mut inner = self.inner.exclusive_access();
+        let current = inner.current_task;  // current是当前运行的任务的编号
+        inner.tasks[current].task_status = TaskStatus::Blocked; // 修改当前运行的任务的状态
+        Self::account_cpu_time(&mut inner.tasks[current]);
+    }
+
+    /// 将处于Blocked状态的任务唤醒为Ready，非Blocked状态则不做任何事
+    fn wake_task(&self, id: usize) {
+        let mut inner = self.inner.exclusive_access();
+        if inner.tasks[id].task_status == TaskStatus::Blocked {
+            inner.tasks[id].task_status = TaskStatus::Ready;
+        }
     }
 
     /// Find next task to run and return task id.
     ///
-    /// In this case, we only return the first `Ready` task in task list.
+    /// Stride scheduling: among all `Ready` tasks, pick the one with the
+    /// smallest stride (ties broken by lowest id).
     fn find_next_task(&self) -> Option<usize> {
         let inner = self.inner.exclusive_access();
-        let current = inner.current_task;   // current是当前运行的任务的编号, 从TaskManagerInner中获取当前运行的任务的编号
-        (current + 1..current + self.num_app + 1)   // 在当前编号范围内寻找下一个状态是TaskStatus::Ready的
-            .map(|id| id % self.num_app)
-            .find(|id| inner.tasks[*id].task_status == TaskStatus::Ready)
+        (0..self.num_app)
+            .filter(|&id| inner.tasks[id].task_status == TaskStatus::Ready)
+            .min_by(|&a, &b| {
+                if stride_less(inner.tasks[a].stride, inner.tasks[b].stride) {
+                    core::cmp::Ordering::Less
+                } else if stride_less(inner.tasks[b].stride, inner.tasks[a].stride) {
+                    core::cmp::Ordering::Greater
+                } else {
+                    core::cmp::Ordering::Equal
+                }
+            })
     }
 
-    /// Switch current `Running` task to the task we have found,
-    /// or there is no `Ready` task and we can exit with all applications completed
+    /// Switch current `Running` task to the task we have found. If there is
+    /// no `Ready` task, either every application has exited (in which case
+    /// we are done) or some task is merely `Blocked` waiting on an event, in
+    /// which case we idle with `wfi` until some caller of `wake_task` makes a
+    /// task `Ready` again. The timer handler here does not wake tasks.
     fn run_next_task(&self) {
-        if let Some(next) = self.find_next_task() {
-            let mut inner = self.inner.exclusive_access();
-            let current = inner.current_task;       // 从TaskManagerInner中获取当前运行的任务的编号
-            inner.tasks[next].task_status = TaskStatus::Running;
-            inner.current_task = next;
-            let current_task_cx_ptr = &mut inner.tasks[current].task_cx as *mut TaskContext;
-            let next_task_cx_ptr = &inner.tasks[next].task_cx as *const TaskContext;
-
-            // 如果要运行的下个任务是首次被调度，记录首次被调度的时间
-            if inner.tasks[next].first_time.is_none() {
-                inner.tasks[next].first_time = Some(get_time_ms());
+        loop {
+            if let Some(next) = self.find_next_task() {
+                self.switch_to(next);
+                return;
             }
+            let inner = self.inner.exclusive_access();
+            let all_exited = (0..self.num_app).all(|id| inner.tasks[id].task_status == TaskStatus::Exited);
             drop(inner);
-            // before this, we should drop local variables that must be dropped manually
+            if all_exited {
+                panic!("All applications completed!");
+            }
+            // Some task is Blocked rather than Exited. This is commonly
+            // reached from inside trap handling, where the CPU enters with
+            // sstatus.SIE cleared, so nothing would ever wake us back up;
+            // re-enable S-mode interrupts around the `wfi` so an interrupt
+            // whose handler calls `wake_task` can actually fire, then restore the interrupt-disabled invariant callers
+            // of `run_next_task` expect before we loop back around.
             unsafe {
-                __switch(current_task_cx_ptr, next_task_cx_ptr);
+                sstatus::set_sie();
+                riscv::asm::wfi();
+                sstatus::clear_sie();
             }
-            // go back to user mode
-        } else {
-            panic!("All applications completed!");
         }
     }
 
+    /// 将next对应的任务换入为Running，更新其stride、时间片等信息后进行上下文切换
+    fn switch_to(&self, next: usize) {
+        let mut inner = self.inner.exclusive_access();
+        let current = inner.current_task;       // 从TaskManagerInner中获取当前运行的任务的编号
+        inner.tasks[next].task_status = TaskStatus::Running;
+        inner.tasks[next].stride = inner.tasks[next].stride.wrapping_add(inner.tasks[next].pass); // 换入后更新其stride
+        inner.current_task = next;
+        let current_task_cx_ptr = &mut inner.tasks[current].task_cx as *mut TaskContext;
+        let next_task_cx_ptr = &inner.tasks[next].task_cx as *const TaskContext;
+
+        inner.tasks[next].scheduled_in = Some(get_time_ms()); // 记录本次换入的时间
+        inner.tasks[next].time_slice = TIME_SLICE; // 重新装满被换入任务的时间片
+        drop(inner);
+        set_next_trigger(); // 为接下来要运行的任务重新设置下一次时钟中断
+        // before this, we should drop local variables that must be dropped manually
+        unsafe {
+            __switch(current_task_cx_ptr, next_task_cx_ptr);
+        }
+        // go back to user mode
+    }
+
     /// 获取当前正在运行任务的系统调用次数信息
     fn get_syscall_times(&self) -> [u32; MAX_SYSCALL_NUM] {
         let inner = self.inner.exclusive_access();
@@ -174,11 +243,32 @@ impl TaskManager {
         inner.tasks[current].task_status
     }
 
+    /// 设置当前任务的调度优先级，并据此重新计算pass
+    fn set_priority(&self, priority: usize) {
+        let mut inner = self.inner.exclusive_access();
+        let current = inner.current_task;
+        inner.tasks[current].priority = priority;
+        inner.tasks[current].pass = BIG_STRIDE / priority as u64; // 优先级越高，pass越小，调度越频繁
+    }
+
     fn get_current_running_time(&self) -> usize {
         let inner = self.inner.exclusive_access();
+        let task = &inner.tasks[inner.current_task];
+        // 加上本次还未结束的运行区间，得到任务实际占用CPU的总时长
+        let live = task.scheduled_in.map_or(0, |scheduled_in| get_time_ms() - scheduled_in);
+        task.cpu_time + live
+    }
+
+    /// 当前任务的时间片减一，返回时间片是否已耗尽(需要被抢占)
+    /// 当前任务不处于Running状态(例如在run_next_task中空转等待)时不计时，返回false
+    fn tick_current_task(&self) -> bool {
+        let mut inner = self.inner.exclusive_access();
         let current = inner.current_task;
-        // 计算当前时间和当前运行任务首次运行的时间差
-        get_time_ms() - inner.tasks[current].first_time.unwrap()
+        if inner.tasks[current].task_status != TaskStatus::Running {
+            return false;
+        }
+        inner.tasks[current].time_slice -= 1;  // 时间片减一
+        inner.tasks[current].time_slice == 0   // 时间片耗尽则返回true
     }
 }
 
@@ -203,6 +293,11 @@ fn mark_current_exited() {
     TASK_MANAGER.mark_current_exited();
 }
 
+/// Change the status of current `Running` task into `Blocked`.
+fn mark_current_blocked() {
+    TASK_MANAGER.mark_current_blocked();
+}
+
 /// Suspend the current 'Running' task and run the next task in task list.
 pub fn suspend_current_and_run_next() {
     mark_current_suspended();
@@ -215,6 +310,17 @@ pub fn exit_current_and_run_next() {
     run_next_task();
 }
 
+/// Block the current 'Running' task and run the next task in task list.
+pub fn block_current_and_run_next() {
+    mark_current_blocked();
+    run_next_task();
+}
+
+/// 唤醒一个Blocked状态的任务，使其重新变为Ready，等待被调度器选中
+pub fn wake_task(id: usize) {
+    TASK_MANAGER.wake_task(id);
+}
+
 /// 维护TaskManager中当前运行的任务的系统调用计数
 pub fn record_syscall_times(syscall_id: usize) {
     TASK_MANAGER.syscall_count(syscall_id);
@@ -230,7 +336,20 @@ pub fn get_status() -> TaskStatus {
     TASK_MANAGER.get_status()
 }
 
-/// 获取当前正在运行的任务距离第一次被调度的时长(单位: ms)
+/// 设置当前任务的调度优先级(必须 >= 2)
+pub fn set_priority(priority: usize) {
+    TASK_MANAGER.set_priority(priority);
+}
+
+/// 获取当前正在运行的任务实际占用CPU的总时长(单位: ms)
 pub fn get_current_running_time() -> usize {
     TASK_MANAGER.get_current_running_time()
+}
+
+/// 时钟中断处理：由trap模块在每次SupervisorTimer中断时调用
+pub fn handle_timer_interrupt() {
+    set_next_trigger(); // 重新设置下一次时钟中断
+    if TASK_MANAGER.tick_current_task() {
+        suspend_current_and_run_next(); // 时间片耗尽，切换到下一个任务
+    }
 }
\ No newline at end of file
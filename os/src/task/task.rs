@@ -1,9 +1,13 @@
 //! Types related to task management
 
-use crate::config::MAX_SYSCALL_NUM;
+use crate::config::{BIG_STRIDE, MAX_SYSCALL_NUM};
 
 use super::TaskContext;
 
+/// Default scheduling priority assigned to every task before it ever calls
+/// `sys_set_priority`
+pub const DEFAULT_PRIORITY: usize = 16;
+
 /// The task control block (TCB) of a task.
 /// 维护任务状态和任务上下文, 两者一并保存在任务控制块的数据结构中
 /// 任务控制块非常重要，在内核中，任务控制块就是应用的管理单位
@@ -16,12 +20,33 @@ pub struct TaskControlBlock {
     pub task_cx: TaskContext,
     /// 记录当前任务的系统调用的次数
     pub syscall_times: [u32; MAX_SYSCALL_NUM],
-    /// 当前任务首次被调度的时间, 通过使用Option记录该任务是否是首次被调度
-    pub first_time: Option<usize>,
+    /// Timestamp of the most recent switch-in; `None` while the task is not
+    /// `Running`, so intervals spent waiting to be rescheduled are never
+    /// folded into `cpu_time`
+    pub scheduled_in: Option<usize>,
+    /// Accumulated CPU time (ms) across every interval this task has spent
+    /// `Running`, excluding time spent waiting after being preempted
+    pub cpu_time: usize,
+    /// Ticks left in the current time slice before this task is preempted,
+    /// reset to [`crate::config::TIME_SLICE`] each time it is switched in
+    pub time_slice: usize,
+    /// Scheduling priority set via `sys_set_priority`, default 16, minimum 2;
+    /// higher priority means a larger share of the CPU
+    pub priority: usize,
+    /// Current stride used by the stride scheduler to pick the next task
+    pub stride: u64,
+    /// Amount `stride` advances by each time this task is scheduled,
+    /// `BIG_STRIDE / priority`, recomputed whenever `priority` changes
+    pub pass: u64,
+}
+
+/// 比较两个stride的先后顺序(容忍u64环绕)，a在b之前(更小)则返回true
+pub fn stride_less(a: u64, b: u64) -> bool {
+    (b.wrapping_sub(a) as i64) > 0
 }
 
 /// The status of a task
-/// 任务运行状态: 未初始化、准备执行、正在执行、已退出
+/// 任务运行状态: 未初始化、准备执行、正在执行、阻塞、已退出
 #[derive(Copy, Clone, PartialEq)]
 pub enum TaskStatus {
     /// uninitialized
@@ -30,6 +55,9 @@ pub enum TaskStatus {
     Ready,
     /// running
     Running,
+    /// blocked, e.g. waiting on an I/O request; skipped by the scheduler
+    /// until something calls `wake_task` on it
+    Blocked,
     /// exited
     Exited,
 }
@@ -0,0 +1,37 @@
+//! Trap context: the register state saved by `__alltraps` and restored by
+//! `__restore` around a trap into/out of the kernel.
+
+use riscv::register::sstatus::{self, Sstatus, SPP};
+
+/// Trap context, laid out to match what `trap.S` pushes onto the kernel
+/// stack.
+#[repr(C)]
+pub struct TrapContext {
+    /// general registers x0~x31
+    pub x: [usize; 32],
+    /// CSR sstatus
+    pub sstatus: Sstatus,
+    /// CSR sepc, the user-mode pc to resume at on `sret`
+    pub sepc: usize,
+}
+
+impl TrapContext {
+    /// Set the saved user stack pointer (x2/sp)
+    pub fn set_sp(&mut self, sp: usize) {
+        self.x[2] = sp;
+    }
+
+    /// Build the trap context an app is first `__restore`d into: SPP::User
+    /// so `sret` drops to user mode, pc at `entry`, sp at `sp`.
+    pub fn app_init_context(entry: usize, sp: usize) -> Self {
+        let mut sstatus = sstatus::read();
+        sstatus.set_spp(SPP::User);
+        let mut cx = Self {
+            x: [0; 32],
+            sstatus,
+            sepc: entry,
+        };
+        cx.set_sp(sp);
+        cx
+    }
+}
@@ -0,0 +1,73 @@
+//! Trap handling: entry into the kernel from user mode, and dispatch of the
+//! resulting syscall, exception or interrupt.
+
+mod context;
+
+use riscv::register::{
+    scause::{self, Exception, Interrupt, Trap},
+    sie, stval, stvec,
+    utvec::TrapMode,
+};
+
+pub use context::TrapContext;
+
+use crate::syscall::syscall;
+use crate::task::{exit_current_and_run_next, handle_timer_interrupt};
+
+extern "C" {
+    fn __alltraps();
+}
+
+/// Point `stvec` at the `__alltraps` trampoline so traps from user mode land
+/// in [`trap_handler`].
+pub fn init() {
+    unsafe {
+        stvec::write(__alltraps as usize, TrapMode::Direct);
+    }
+}
+
+/// Enable the S-mode timer interrupt so a task that runs past its time
+/// slice can be preempted; see `crate::task::handle_timer_interrupt`.
+pub fn enable_timer_interrupt() {
+    unsafe {
+        sie::set_stimer();
+    }
+}
+
+/// Dispatch a trap taken from user mode.
+#[no_mangle]
+pub fn trap_handler(cx: &mut TrapContext) -> &mut TrapContext {
+    let scause = scause::read();
+    let stval = stval::read();
+    match scause.cause() {
+        Trap::Exception(Exception::UserEnvCall) => {
+            cx.sepc += 4;
+            cx.x[10] = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]) as usize;
+        }
+        Trap::Interrupt(Interrupt::SupervisorTimer) => {
+            handle_timer_interrupt();
+        }
+        Trap::Exception(Exception::StoreFault)
+        | Trap::Exception(Exception::StorePageFault)
+        | Trap::Exception(Exception::InstructionFault)
+        | Trap::Exception(Exception::InstructionPageFault)
+        | Trap::Exception(Exception::LoadFault)
+        | Trap::Exception(Exception::LoadPageFault) => {
+            error!(
+                "[kernel] {:?} in application, bad addr = {:#x}, bad instruction = {:#x}, core dumped.",
+                scause.cause(),
+                stval,
+                cx.sepc
+            );
+            exit_current_and_run_next();
+        }
+        Trap::Exception(Exception::IllegalInstruction) => {
+            error!("[kernel] IllegalInstruction in application, core dumped.");
+            exit_current_and_run_next();
+        }
+        _ => {
+            panic!("Unsupported trap {:?}, stval = {:#x}!", scause.cause(), stval);
+        }
+    }
+    cx
+}